@@ -1,15 +1,30 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")] // hide console window on Windows in release
 
-use std::{f32, io, ops::RangeInclusive, path::Path};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    f32, io,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
 
 use eframe::egui::{self, Color32, Response, ScrollArea, TextWrapMode, Vec2, Vec2b};
-use egui_plot::{GridMark, Line, Plot, PlotPoint, PlotPoints};
+use egui_plot::{GridMark, Line, Plot, PlotPoint, PlotPoints, Polygon};
 use jiff::{Unit, fmt::strtime, tz::TimeZone};
 
 const PLOT_WIDTH: f32 = 450.0;
 const PLOT_HEIGHT: f32 = 120.0;
 const PLOT_LINK_AXIS_NAME: &'static str = "linked";
 const PLOT_SPACE: f32 = 8.0;
+const SONNERIE_PATH: &str = "../aranet2sonnerie/measurements.son";
+const DEFAULT_AUTO_REFRESH_SECS: u64 = 60;
+const LTTB_TARGET_POINTS: usize = 450;
+/// Gap, in ppm, between the "alert on" and "alert off" thresholds so a value
+/// oscillating around the user-set limit doesn't flip the alert every sample.
+const CO2_ALERT_HYSTERESIS_PPM: f32 = 50.0;
 
 fn main() -> eframe::Result {
     env_logger::init(); // Log to stderr (if you run with `RUST_LOG=debug`).
@@ -23,34 +38,423 @@ fn main() -> eframe::Result {
 
     // Our application state:
     let mut linked_axes_demo = LinkedAxesDemo::default();
-    linked_axes_demo.refresh().unwrap();
 
     eframe::run_simple_native("Aranet4", options, move |ctx, _frame| {
+        linked_axes_demo.poll_worker();
+
         egui::CentralPanel::default().show(ctx, |ui| {
             ui.heading("Aranet4");
-            if ui.button("Refresh").clicked() {
-                linked_axes_demo.refresh().unwrap();
-            }
+            ui.horizontal(|ui| {
+                if ui.button("Refresh").clicked() {
+                    linked_axes_demo.refresh();
+                }
+                if ui
+                    .checkbox(&mut linked_axes_demo.auto_refresh, "Auto-refresh every")
+                    .changed()
+                {
+                    linked_axes_demo.send_auto_refresh_config();
+                }
+                if ui
+                    .add(
+                        egui::DragValue::new(&mut linked_axes_demo.auto_refresh_interval_secs)
+                            .range(1..=3600)
+                            .suffix("s"),
+                    )
+                    .changed()
+                {
+                    linked_axes_demo.send_auto_refresh_config();
+                }
+                if ui.button("Export PNG").clicked() {
+                    linked_axes_demo.request_export(ctx);
+                }
+                ui.label("CO₂ alert limit");
+                ui.add(
+                    egui::DragValue::new(&mut linked_axes_demo.co2_alert_limit)
+                        .range(0.0..=5000.0)
+                        .suffix(" ppm"),
+                );
+            });
             linked_axes_demo.ui(ui);
         });
+
+        linked_axes_demo.poll_export(ctx);
+
+        if linked_axes_demo.auto_refresh {
+            ctx.request_repaint_after(Duration::from_secs(linked_axes_demo.auto_refresh_interval_secs));
+        }
     })
 }
 
-#[derive(Default)]
+enum WorkerCommand {
+    /// Re-read and re-parse the whole series right away.
+    Refresh,
+    /// Enable or disable periodic tailing, and at which interval.
+    SetAutoRefresh { enabled: bool, interval: Duration },
+}
+
+enum WorkerUpdate {
+    /// Replaces `records` wholesale, as produced by an explicit `Refresh`.
+    Full(Vec<Record>),
+    /// Records newer than anything seen so far, to be appended in place.
+    Append(Vec<Record>),
+}
+
+/// Reads and parses the sonnerie database off the UI thread, publishing updates back
+/// through `updates_tx` so the UI never blocks on disk I/O. When auto-refresh is
+/// enabled, tails the database on `interval`, forwarding only records newer than the
+/// last `timestamp_nanos` seen so a long history doesn't mean re-sending (and
+/// re-rendering) data the UI already has.
+///
+/// TODO(follow-up): each tick still does a full `fetch_records` — every row in the
+/// series is re-read and re-parsed before the already-seen prefix is filtered out
+/// below. `sonnerie::DatabaseReader`/`Record` exposed no range- or offset-based read in
+/// what this worker was written against, only a from-scratch iterator per `get`. If a
+/// newer sonnerie release (or an API this wasn't written against) supports resuming a
+/// series read from a timestamp or row offset, tailing should use it instead, so the
+/// re-parse cost actually drops with `interval` the way it does for the UI-facing cost.
+fn worker(command_rx: mpsc::Receiver<WorkerCommand>, updates_tx: mpsc::Sender<WorkerUpdate>) {
+    let path = Path::new(SONNERIE_PATH);
+    let mut auto_refresh = false;
+    let mut interval = Duration::from_secs(DEFAULT_AUTO_REFRESH_SECS);
+    let mut last_timestamp_nanos: Option<u64> = None;
+
+    loop {
+        let command = if auto_refresh {
+            match command_rx.recv_timeout(interval) {
+                Ok(command) => Some(command),
+                Err(mpsc::RecvTimeoutError::Timeout) => None,
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            }
+        } else {
+            match command_rx.recv() {
+                Ok(command) => Some(command),
+                Err(_) => break,
+            }
+        };
+
+        match command {
+            Some(WorkerCommand::Refresh) => match fetch_records(path) {
+                Ok(records) => {
+                    last_timestamp_nanos = records.last().map(|record| record.timestamp_nanos);
+                    if updates_tx.send(WorkerUpdate::Full(records)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => log::error!("failed to refresh records: {err}"),
+            },
+            Some(WorkerCommand::SetAutoRefresh { enabled, interval: new_interval }) => {
+                auto_refresh = enabled;
+                interval = new_interval;
+            }
+            None => match fetch_records(path) {
+                Ok(records) => {
+                    let new_records: Vec<_> = records
+                        .into_iter()
+                        .filter(|record| {
+                            last_timestamp_nanos
+                                .is_none_or(|last| record.timestamp_nanos > last)
+                        })
+                        .collect();
+                    if let Some(newest) = new_records.last() {
+                        last_timestamp_nanos = Some(newest.timestamp_nanos);
+                    }
+                    if !new_records.is_empty() && updates_tx.send(WorkerUpdate::Append(new_records)).is_err() {
+                        break;
+                    }
+                }
+                Err(err) => log::error!("failed to tail records: {err}"),
+            },
+        }
+    }
+}
+
+fn fetch_records(path: &Path) -> io::Result<Vec<Record>> {
+    let db = sonnerie::DatabaseReader::new(path)?;
+    let reader = db.get("aranet4");
+    reader.into_iter().map(Record::try_from).collect()
+}
+
+/// Reduces `points` to roughly `target` points using the Largest-Triangle-Three-Buckets
+/// algorithm, preserving the visual shape of the series. Always keeps the first and
+/// last point; each bucket in between keeps whichever point forms the largest triangle
+/// with the previously selected point and the average of the next bucket.
+fn lttb(points: &[[f64; 2]], target: usize) -> Vec<[f64; 2]> {
+    if target >= points.len() || target < 3 {
+        return points.to_vec();
+    }
+
+    let mut sampled = Vec::with_capacity(target);
+    sampled.push(points[0]);
+
+    let bucket_size = (points.len() - 2) as f64 / (target - 2) as f64;
+    let mut selected = 0;
+
+    for i in 0..target - 2 {
+        let bucket_start = (i as f64 * bucket_size) as usize + 1;
+        let bucket_end = (((i + 1) as f64 * bucket_size) as usize + 1).min(points.len() - 1);
+
+        let next_bucket_start = bucket_end;
+        let next_bucket_end = (((i + 2) as f64 * bucket_size) as usize + 1).min(points.len());
+        let next_bucket = &points[next_bucket_start..next_bucket_end];
+        let [avg_x, avg_y] = if next_bucket.is_empty() {
+            points[points.len() - 1]
+        } else {
+            let sum = next_bucket
+                .iter()
+                .fold([0.0, 0.0], |acc, point| [acc[0] + point[0], acc[1] + point[1]]);
+            [sum[0] / next_bucket.len() as f64, sum[1] / next_bucket.len() as f64]
+        };
+
+        let point_a = points[selected];
+        let mut best_area = -1.0;
+        let mut best_index = bucket_start;
+        for (offset, point_b) in points[bucket_start..bucket_end].iter().enumerate() {
+            let area = ((point_a[0] - avg_x) * (point_b[1] - point_a[1])
+                - (point_a[0] - point_b[0]) * (avg_y - point_a[1]))
+                .abs()
+                / 2.0;
+            if area > best_area {
+                best_area = area;
+                best_index = bucket_start + offset;
+            }
+        }
+
+        sampled.push(points[best_index]);
+        selected = best_index;
+    }
+
+    sampled.push(points[points.len() - 1]);
+    sampled
+}
+
+/// Builds a default export file name embedding the visible time range, e.g.
+/// `aranet4_20260701-0800_20260726-1230.png`. `visible_x_range` is the linked plots'
+/// current x-bounds (millisecond timestamps), not the full record history, so zooming
+/// into a narrower window is reflected in the exported name.
+fn export_file_name(visible_x_range: Option<(f64, f64)>) -> String {
+    let format_millis = |timestamp_millis: f64| -> String {
+        let nanosecond = (timestamp_millis * 1_000_000.0) as i128;
+        let timestamp = jiff::Timestamp::from_nanosecond(nanosecond).unwrap();
+        let datetime = timestamp.to_zoned(TimeZone::UTC);
+        strtime::format("%Y%m%d-%H%M", &datetime).unwrap()
+    };
+
+    match visible_x_range {
+        Some((x_min, x_max)) => {
+            format!("aranet4_{}_{}.png", format_millis(x_min), format_millis(x_max))
+        }
+        None => "aranet4.png".to_owned(),
+    }
+}
+
+/// Encodes a captured `ColorImage` as PNG and writes it to `path`.
+fn save_screenshot(image: &egui::ColorImage, path: &Path) -> image::ImageResult<()> {
+    let pixels: Vec<u8> = image.pixels.iter().flat_map(|color| color.to_array()).collect();
+    let buffer = image::RgbaImage::from_raw(image.size[0] as u32, image.size[1] as u32, pixels)
+        .expect("ColorImage pixel buffer matches its reported size");
+    buffer.save_with_format(path, image::ImageFormat::Png)
+}
+
+/// Fires a native desktop notification announcing that CO₂ crossed the alert limit.
+fn notify_co2_alert(co2_level: u32, above_limit: bool) {
+    let (summary, body) = if above_limit {
+        ("CO₂ level high", format!("CO₂ reached {co2_level} ppm — consider ventilating."))
+    } else {
+        ("CO₂ level back to normal", format!("CO₂ dropped back to {co2_level} ppm."))
+    };
+
+    if let Err(err) = notify_rust::Notification::new().summary(summary).body(&body).show() {
+        log::error!("failed to send desktop notification: {err}");
+    }
+}
+
+/// Ventilation-quality bands for a series: green below `good_below`, amber up to
+/// `poor_above`, red beyond it.
+#[derive(Clone, Copy)]
+struct Thresholds {
+    good_below: f64,
+    poor_above: f64,
+}
+
+impl Thresholds {
+    const CO2: Thresholds = Thresholds { good_below: 800.0, poor_above: 1400.0 };
+    const TEMPERATURE: Thresholds = Thresholds { good_below: 26.0, poor_above: 30.0 };
+    const HUMIDITY: Thresholds = Thresholds { good_below: 60.0, poor_above: 70.0 };
+
+    fn bands(&self) -> [(f64, f64, Color32); 3] {
+        [
+            (f64::NEG_INFINITY, self.good_below, Color32::from_rgba_unmultiplied(0, 200, 0, 40)),
+            (self.good_below, self.poor_above, Color32::from_rgba_unmultiplied(255, 191, 0, 40)),
+            (self.poor_above, f64::INFINITY, Color32::from_rgba_unmultiplied(200, 0, 0, 40)),
+        ]
+    }
+}
+
 struct LinkedAxesDemo {
     records: Vec<Record>,
+    command_tx: mpsc::Sender<WorkerCommand>,
+    updates_rx: mpsc::Receiver<WorkerUpdate>,
+    auto_refresh: bool,
+    auto_refresh_interval_secs: u64,
+    co2_thresholds: Thresholds,
+    temperature_thresholds: Thresholds,
+    humidity_thresholds: Thresholds,
+    co2_alert_limit: f32,
+    co2_alerted: bool,
+    downsample_cache: RefCell<HashMap<DownsampleKey, Vec<[f64; 2]>>>,
+    export_path: Option<PathBuf>,
+    /// Visible x-range (millisecond timestamps) of the linked plots, as of the last
+    /// frame drawn. All plots share the same linked x-axis, so any one of them
+    /// observing `plot_bounds()` keeps this current for the others, e.g. for export.
+    visible_x_range: Cell<Option<(f64, f64)>>,
+}
+
+/// Identifies a downsampled series: which plot, what visible x-range, how much data
+/// went in, and to how many points it was reduced. A cache hit means panning or
+/// zooming the linked axes doesn't re-run LTTB every frame. The x-range is quantized
+/// (see `quantize_plot_bound`) so that small, sub-pixel pans during an active drag
+/// still land on the same key instead of missing on every frame.
+#[derive(PartialEq, Eq, Hash)]
+struct DownsampleKey {
+    title: String,
+    x_min_q: i64,
+    x_max_q: i64,
+    record_count: usize,
+    target: usize,
+}
+
+/// Number of quantization steps spanning the visible x-range. Coarser than the plot's
+/// pixel width on purpose: nearby frames during a pan/zoom should collide onto the same
+/// bucket rather than each producing a fresh cache entry.
+const DOWNSAMPLE_QUANTIZE_STEPS: f64 = 64.0;
+
+/// Upper bound on how many distinct ranges/series we keep downsampled data for at once.
+/// Exceeded only by a session with a lot of independent pan/zoom history; cleared
+/// wholesale rather than tracking per-entry recency, matching the cache's simplicity.
+const DOWNSAMPLE_CACHE_CAP: usize = 64;
+
+/// Returns the index of the multiple of `span / DOWNSAMPLE_QUANTIZE_STEPS` nearest to a
+/// plot x-bound, so that frames with nearly identical bounds (e.g. during an active pan) hash to the
+/// same `DownsampleKey`.
+fn quantize_plot_bound(value: f64, span: f64) -> i64 {
+    let step = (span / DOWNSAMPLE_QUANTIZE_STEPS).max(f64::EPSILON);
+    (value / step).round() as i64
+}
+
+impl Default for LinkedAxesDemo {
+    fn default() -> Self {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (updates_tx, updates_rx) = mpsc::channel();
+        thread::spawn(move || worker(command_rx, updates_tx));
+
+        let demo = LinkedAxesDemo {
+            records: Vec::new(),
+            command_tx,
+            updates_rx,
+            auto_refresh: false,
+            auto_refresh_interval_secs: DEFAULT_AUTO_REFRESH_SECS,
+            co2_thresholds: Thresholds::CO2,
+            temperature_thresholds: Thresholds::TEMPERATURE,
+            humidity_thresholds: Thresholds::HUMIDITY,
+            co2_alert_limit: Thresholds::CO2.poor_above as f32,
+            co2_alerted: false,
+            downsample_cache: RefCell::new(HashMap::new()),
+            export_path: None,
+            visible_x_range: Cell::new(None),
+        };
+        demo.refresh();
+        demo
+    }
 }
 
 impl LinkedAxesDemo {
-    fn refresh(&mut self) -> io::Result<()> {
-        let path = Path::new("../aranet2sonnerie/measurements.son");
-        let db = sonnerie::DatabaseReader::new(&path)?;
-        let reader = db.get("aranet4");
-        self.records = reader
-            .into_iter()
-            .map(Record::try_from)
-            .collect::<io::Result<_>>()?;
-        Ok(())
+    /// Asks the background worker to re-read the database. Returns immediately;
+    /// the result is picked up by `poll_worker` once the worker publishes it.
+    fn refresh(&self) {
+        self.command_tx.send(WorkerCommand::Refresh).ok();
+    }
+
+    /// Forwards the current auto-refresh toggle and interval to the worker.
+    fn send_auto_refresh_config(&self) {
+        self.command_tx
+            .send(WorkerCommand::SetAutoRefresh {
+                enabled: self.auto_refresh,
+                interval: Duration::from_secs(self.auto_refresh_interval_secs),
+            })
+            .ok();
+    }
+
+    /// Drains any updates published by the worker since the last frame. Never blocks.
+    fn poll_worker(&mut self) {
+        while let Ok(update) = self.updates_rx.try_recv() {
+            match update {
+                WorkerUpdate::Full(records) => {
+                    self.records = records;
+                    self.downsample_cache.borrow_mut().clear();
+                    if let Some(newest) = self.records.last() {
+                        self.check_co2_alert(newest.co2_level);
+                    }
+                }
+                WorkerUpdate::Append(new_records) => {
+                    for record in &new_records {
+                        self.check_co2_alert(record.co2_level);
+                    }
+                    self.records.extend(new_records);
+                    self.downsample_cache.borrow_mut().clear();
+                }
+            }
+        }
+    }
+
+    /// Edge-triggers a desktop notification when `co2_level` crosses `co2_alert_limit`,
+    /// in either direction. Uses a hysteresis band rather than a single threshold: once
+    /// alerted, the level must drop `CO2_ALERT_HYSTERESIS_PPM` below the limit before the
+    /// alert clears, so a value oscillating right around the limit doesn't spam.
+    fn check_co2_alert(&mut self, co2_level: u32) {
+        let co2_level = co2_level as f32;
+        let alert_on = co2_level >= self.co2_alert_limit;
+        let alert_off = co2_level <= self.co2_alert_limit - CO2_ALERT_HYSTERESIS_PPM;
+
+        if !self.co2_alerted && alert_on {
+            self.co2_alerted = true;
+            notify_co2_alert(co2_level as u32, true);
+        } else if self.co2_alerted && alert_off {
+            self.co2_alerted = false;
+            notify_co2_alert(co2_level as u32, false);
+        }
+    }
+
+    /// Asks the user where to save a snapshot, then requests a screenshot of the
+    /// current frame; the result is picked up by `poll_export` once it arrives.
+    fn request_export(&mut self, ctx: &egui::Context) {
+        let default_name = export_file_name(self.visible_x_range.get());
+        let Some(path) = rfd::FileDialog::new()
+            .set_file_name(&default_name)
+            .add_filter("PNG image", &["png"])
+            .save_file()
+        else {
+            return;
+        };
+        self.export_path = Some(path);
+        ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot(Default::default()));
+    }
+
+    /// Writes the pending screenshot to disk once eframe delivers it for this frame.
+    fn poll_export(&mut self, ctx: &egui::Context) {
+        if self.export_path.is_none() {
+            return;
+        }
+        ctx.input(|input| {
+            for event in &input.raw.events {
+                if let egui::Event::Screenshot { image, .. } = event {
+                    if let Some(path) = self.export_path.take() {
+                        if let Err(err) = save_screenshot(image, &path) {
+                            log::error!("failed to export screenshot: {err}");
+                        }
+                    }
+                }
+            }
+        });
     }
 
     fn ui(&mut self, ui: &mut egui::Ui) -> Response {
@@ -79,6 +483,7 @@ impl LinkedAxesDemo {
         y_axis_formatter: impl Fn(GridMark, &RangeInclusive<f64>) -> String,
         x_axis_formatter: impl Fn(GridMark, &RangeInclusive<f64>) -> String,
         show_y_axis: bool,
+        thresholds: Option<Thresholds>,
         record_point_extractor: impl Fn(&Record) -> f64,
     ) -> Response {
         let link_group_id = ui.id().with(PLOT_LINK_AXIS_NAME);
@@ -94,11 +499,61 @@ impl LinkedAxesDemo {
             .y_axis_min_width(60.0)
             .y_axis_formatter(y_axis_formatter)
             .show(ui, |plot_ui| {
-                let data = self
-                    .records
-                    .iter()
-                    .map(|record| [record.timestamp_millis(), record_point_extractor(record)])
-                    .collect();
+                if let Some(thresholds) = thresholds {
+                    let bounds = plot_ui.plot_bounds();
+                    let (x_min, x_max) = (bounds.min()[0], bounds.max()[0]);
+                    for (y_min, y_max, color) in thresholds.bands() {
+                        let y_min = y_min.max(bounds.min()[1]);
+                        let y_max = y_max.min(bounds.max()[1]);
+                        if y_min >= y_max {
+                            continue;
+                        }
+                        let band = Polygon::new(
+                            "",
+                            PlotPoints::new(vec![
+                                [x_min, y_min],
+                                [x_max, y_min],
+                                [x_max, y_max],
+                                [x_min, y_max],
+                            ]),
+                        )
+                        .fill_color(color)
+                        .stroke(egui::Stroke::NONE);
+                        plot_ui.polygon(band);
+                    }
+                }
+
+                let bounds = plot_ui.plot_bounds();
+                let (x_min, x_max) = (bounds.min()[0], bounds.max()[0]);
+                self.visible_x_range.set(Some((x_min, x_max)));
+
+                let span = x_max - x_min;
+                let key = DownsampleKey {
+                    title: title.to_owned(),
+                    x_min_q: quantize_plot_bound(x_min, span),
+                    x_max_q: quantize_plot_bound(x_max, span),
+                    record_count: self.records.len(),
+                    target: LTTB_TARGET_POINTS,
+                };
+                let mut cache = self.downsample_cache.borrow_mut();
+                if cache.len() >= DOWNSAMPLE_CACHE_CAP {
+                    cache.clear();
+                }
+                let data = cache
+                    .entry(key)
+                    .or_insert_with(|| {
+                        let points: Vec<[f64; 2]> = self
+                            .records
+                            .iter()
+                            .map(|record| {
+                                [record.timestamp_millis(), record_point_extractor(record)]
+                            })
+                            .filter(|point| point[0] >= x_min && point[0] <= x_max)
+                            .collect();
+                        lttb(&points, LTTB_TARGET_POINTS)
+                    })
+                    .clone();
+                drop(cache);
                 let line = Line::new(title, PlotPoints::new(data))
                     .fill(-100.0)
                     .color(line_color);
@@ -163,6 +618,7 @@ impl LinkedAxesDemo {
                 format!("{timestamp:#}")
             },
             false,
+            Some(self.temperature_thresholds),
             |record| record.celsius as f64,
         )
     }
@@ -187,6 +643,7 @@ impl LinkedAxesDemo {
                 format!("{timestamp:#}")
             },
             false,
+            None,
             |record| record.pressure as f64,
         )
     }
@@ -211,6 +668,7 @@ impl LinkedAxesDemo {
                 format!("{timestamp:#}")
             },
             false,
+            Some(self.co2_thresholds),
             |record| record.co2_level as f64,
         )
     }
@@ -235,6 +693,7 @@ impl LinkedAxesDemo {
                 format!("{timestamp:#}")
             },
             false,
+            Some(self.humidity_thresholds),
             |record| record.humidity as f64,
         )
     }
@@ -259,6 +718,7 @@ impl LinkedAxesDemo {
                 format!("{timestamp:#}")
             },
             true,
+            None,
             |record| record.battery as f64,
         )
     }